@@ -0,0 +1,250 @@
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, MouseEvent};
+use yew::prelude::*;
+use yew_agent::{Bridge, Bridged};
+
+use crate::components::chat::{DrawBatch, MsgTypes, StrokePoint, WebSocketMessage};
+use crate::services::event_bus::EventBus;
+
+const PALETTE: &[&str] = &["#111827", "#ef4444", "#22c55e", "#3b82f6", "#eab308", "#ffffff"];
+const CANVAS_WIDTH: f64 = 480.0;
+const CANVAS_HEIGHT: f64 = 360.0;
+
+/// Minimum time between network-bound draw batches; local rendering still
+/// happens on every pointer-move event, so the stroke stays smooth while a
+/// fast drag sends at most ~30 batches/sec instead of one per frame.
+const POINTER_THROTTLE_MS: f64 = 32.0;
+
+fn generate_client_id() -> String {
+    format!("{:x}", (js_sys::Math::random() * 1e15) as u64)
+}
+
+pub enum Msg {
+    PointerDown(f64, f64),
+    PointerMove(f64, f64),
+    PointerUp,
+    SelectColor(&'static str),
+    Clear,
+    HandleBusMsg(String),
+}
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    /// Lets the parent `Chat` forward a batch onto the shared WebSocket; the
+    /// canvas itself knows nothing about rooms or the wire protocol.
+    pub on_draw: Callback<DrawBatch>,
+}
+
+pub struct Canvas {
+    canvas_ref: NodeRef,
+    selected_color: &'static str,
+    last_point: Option<(f64, f64)>,
+    last_emit: f64,
+    /// Points drawn locally since the last emitted batch. While throttled,
+    /// each new point is appended here instead of being dropped, so the
+    /// batch that finally goes out still carries every point of the stroke
+    /// and remote peers don't see a gapped line on a fast drag.
+    pending_points: Vec<StrokePoint>,
+    /// Tags every outgoing batch so `HandleBusMsg` can recognize and skip
+    /// this client's own strokes when the server echoes them back.
+    client_id: String,
+    _producer: Box<dyn Bridge<EventBus>>,
+}
+
+impl Canvas {
+    fn context(&self) -> Option<CanvasRenderingContext2d> {
+        let canvas = self.canvas_ref.cast::<HtmlCanvasElement>()?;
+        canvas
+            .get_context("2d")
+            .ok()
+            .flatten()
+            .map(|ctx| ctx.unchecked_into::<CanvasRenderingContext2d>())
+    }
+
+    fn draw_segment(&self, from: (f64, f64), to: (f64, f64), color: &str) {
+        let Some(ctx) = self.context() else {
+            return;
+        };
+        ctx.set_stroke_style(&JsValue::from_str(color));
+        ctx.set_line_width(3.0);
+        ctx.set_line_cap("round");
+        ctx.begin_path();
+        ctx.move_to(from.0, from.1);
+        ctx.line_to(to.0, to.1);
+        ctx.stroke();
+    }
+
+    fn clear_canvas(&self) {
+        if let Some(ctx) = self.context() {
+            ctx.clear_rect(0.0, 0.0, CANVAS_WIDTH, CANVAS_HEIGHT);
+        }
+    }
+
+    fn replay(&self, batch: &DrawBatch) {
+        if batch.clear {
+            self.clear_canvas();
+            return;
+        }
+        for pair in batch.points.windows(2) {
+            let (a, b) = (&pair[0], &pair[1]);
+            if a.color != b.color {
+                continue;
+            }
+            self.draw_segment(
+                (a.x * CANVAS_WIDTH, a.y * CANVAS_HEIGHT),
+                (b.x * CANVAS_WIDTH, b.y * CANVAS_HEIGHT),
+                &b.color,
+            );
+        }
+    }
+}
+
+impl Component for Canvas {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        Self {
+            canvas_ref: NodeRef::default(),
+            selected_color: PALETTE[0],
+            last_point: None,
+            last_emit: 0.0,
+            pending_points: vec![],
+            client_id: generate_client_id(),
+            _producer: EventBus::bridge(ctx.link().callback(Msg::HandleBusMsg)),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::PointerDown(x, y) => {
+                self.last_point = Some((x, y));
+                self.pending_points.clear();
+                false
+            }
+            Msg::PointerMove(x, y) => {
+                let Some(last) = self.last_point else {
+                    return false;
+                };
+                self.draw_segment(last, (x, y), self.selected_color);
+                self.last_point = Some((x, y));
+
+                if self.pending_points.is_empty() {
+                    self.pending_points.push(StrokePoint {
+                        color: self.selected_color.to_string(),
+                        x: last.0 / CANVAS_WIDTH,
+                        y: last.1 / CANVAS_HEIGHT,
+                    });
+                }
+                self.pending_points.push(StrokePoint {
+                    color: self.selected_color.to_string(),
+                    x: x / CANVAS_WIDTH,
+                    y: y / CANVAS_HEIGHT,
+                });
+
+                let now = js_sys::Date::now();
+                if now - self.last_emit < POINTER_THROTTLE_MS {
+                    return false;
+                }
+                self.last_emit = now;
+
+                let batch = DrawBatch {
+                    points: std::mem::take(&mut self.pending_points),
+                    clear: false,
+                    sender: self.client_id.clone(),
+                };
+                ctx.props().on_draw.emit(batch);
+                false
+            }
+            Msg::PointerUp => {
+                self.last_point = None;
+                if !self.pending_points.is_empty() {
+                    self.last_emit = js_sys::Date::now();
+                    ctx.props().on_draw.emit(DrawBatch {
+                        points: std::mem::take(&mut self.pending_points),
+                        clear: false,
+                        sender: self.client_id.clone(),
+                    });
+                }
+                false
+            }
+            Msg::SelectColor(color) => {
+                self.selected_color = color;
+                true
+            }
+            Msg::Clear => {
+                self.clear_canvas();
+                ctx.props().on_draw.emit(DrawBatch {
+                    points: vec![],
+                    clear: true,
+                    sender: self.client_id.clone(),
+                });
+                false
+            }
+            Msg::HandleBusMsg(s) => {
+                let Ok(envelope) = serde_json::from_str::<WebSocketMessage>(&s) else {
+                    return false;
+                };
+                if !matches!(envelope.message_type, MsgTypes::Draw) {
+                    return false;
+                }
+                let Some(data) = envelope.data else {
+                    return false;
+                };
+                let Ok(batch) = serde_json::from_str::<DrawBatch>(&data) else {
+                    return false;
+                };
+                if !batch.sender.is_empty() && batch.sender == self.client_id {
+                    // This is our own stroke echoed back by the server.
+                    return false;
+                }
+                self.replay(&batch);
+                false
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let pointer_down = ctx.link().callback(|e: MouseEvent| {
+            Msg::PointerDown(e.offset_x() as f64, e.offset_y() as f64)
+        });
+        let pointer_move = ctx.link().callback(|e: MouseEvent| {
+            Msg::PointerMove(e.offset_x() as f64, e.offset_y() as f64)
+        });
+        let pointer_up = ctx.link().callback(|_: MouseEvent| Msg::PointerUp);
+        let clear = ctx.link().callback(|_| Msg::Clear);
+
+        html! {
+            <div class="flex flex-col items-start gap-2 p-3">
+                <div class="flex gap-2 items-center">
+                    {
+                        PALETTE.iter().map(|color| {
+                            let select = ctx.link().callback(move |_| Msg::SelectColor(color));
+                            let ring = if *color == self.selected_color { "ring-2 ring-offset-1 ring-blue-500" } else { "" };
+                            html! {
+                                <button
+                                    onclick={select}
+                                    class={format!("w-6 h-6 rounded-full border {ring}")}
+                                    style={format!("background-color: {color}")}
+                                />
+                            }
+                        }).collect::<Html>()
+                    }
+                    <button onclick={clear} class="ml-2 text-xs px-2 py-1 rounded bg-gray-200 hover:bg-gray-300">
+                        {"Clear"}
+                    </button>
+                </div>
+                <canvas
+                    ref={self.canvas_ref.clone()}
+                    width={CANVAS_WIDTH.to_string()}
+                    height={CANVAS_HEIGHT.to_string()}
+                    class="bg-white rounded-lg border touch-none"
+                    onmousedown={pointer_down}
+                    onmousemove={pointer_move}
+                    onmouseup={pointer_up.clone()}
+                    onmouseleave={pointer_up}
+                />
+            </div>
+        }
+    }
+}
@@ -1,21 +1,106 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use gloo::timers::callback::Timeout;
+use pulldown_cmark::{Event, Parser, Tag};
 use serde::{Deserialize, Serialize};
-use web_sys::HtmlInputElement;
+use wasm_bindgen_futures::spawn_local;
+use web_sys::{HtmlInputElement, HtmlVideoElement, MediaStream};
 use yew::prelude::*;
 use yew_agent::{Bridge, Bridged};
 
 use crate::{User, services::websocket::WebsocketService};
+use crate::components::canvas::Canvas;
+use crate::services::call::CallService;
 use crate::services::event_bus::EventBus;
+use crate::services::history::HistoryStore;
 
 pub enum Msg {
     HandleMsg(String),
     SubmitMessage,
     ToggleDarkMode,
+    Typing,
+    StoppedTyping,
+    /// Fired by `WebsocketService` after every successful handshake,
+    /// including the first one, so session state gets (re-)announced to a
+    /// freshly (re-)opened socket.
+    Reconnected,
+    SwitchRoom(RoomId),
+    SendDraw(DrawBatch),
+    StartCall(String),
+    AcceptCall,
+    DeclineCall,
+    HangUp,
+    LocalStreamReady(MediaStream),
+    RemoteStreamReady(MediaStream),
+    LocalIceCandidate(String, Option<String>, Option<u16>),
+    SendCallOffer(String, String),
+    SendCallAnswer(String, String),
+    CallConnected,
+}
+
+pub(crate) type RoomId = String;
+
+#[derive(Clone, Debug)]
+struct Room {
+    id: RoomId,
+    name: String,
+    unread: usize,
+}
+
+/// Rooms available from the switcher. There's no create-room flow yet, so
+/// this is a fixed list rather than something driven by server state.
+const KNOWN_ROOMS: &[(&str, &str)] = &[
+    ("lobby", "Lobby"),
+    ("random", "Random"),
+    ("help", "Help"),
+];
+
+/// Debounce window: once this long passes without another keystroke, we
+/// consider the user to have stopped typing and broadcast that.
+const TYPING_DEBOUNCE_MS: u32 = 1500;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum PresenceState {
+    #[default]
+    Online,
+    Away,
+    Offline,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct PresencePayload {
+    user: String,
+    status: PresenceState,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct TypingPayload {
+    user: String,
+    typing: bool,
 }
 
-#[derive(Deserialize)]
-struct MessageData {
+/// Identifies a message relayed into the room by a server-side bridge from
+/// another network, rather than typed by a locally-registered user.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct Origin {
+    platform: String,
+    author: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub(crate) struct MessageData {
     from: String,
     message: String,
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    timestamp: Option<f64>,
+    #[serde(default)]
+    room: Option<RoomId>,
+    #[serde(default)]
+    origin: Option<Origin>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -24,29 +109,410 @@ pub enum MsgTypes {
     Users,
     Register,
     Message,
+    History,
+    Presence,
+    Typing,
+    JoinRoom,
+    LeaveRoom,
+    Draw,
+    CallOffer,
+    CallAnswer,
+    IceCandidate,
+    CallEnd,
+}
+
+/// Signaling payload relayed over the existing WebSocket for
+/// `CallOffer`/`CallAnswer`/`IceCandidate`. `sdp` carries the session
+/// description for offers/answers; `candidate` (plus its `sdp_mid`/
+/// `sdp_m_line_index` companions, required by `addIceCandidate`) carries one
+/// ICE candidate.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct CallSignal {
+    from: String,
+    to: String,
+    #[serde(default)]
+    sdp: Option<String>,
+    #[serde(default)]
+    candidate: Option<String>,
+    #[serde(default)]
+    sdp_mid: Option<String>,
+    #[serde(default)]
+    sdp_m_line_index: Option<u16>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+enum CallState {
+    #[default]
+    Idle,
+    Outgoing {
+        with: String,
+    },
+    Incoming {
+        with: String,
+        offer_sdp: String,
+    },
+    Connected {
+        with: String,
+    },
+}
+
+/// A single point on the shared sketch canvas, normalized to 0..1 so it
+/// replays correctly regardless of the receiver's canvas size.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct StrokePoint {
+    pub(crate) color: String,
+    pub(crate) x: f64,
+    pub(crate) y: f64,
+}
+
+/// Payload for `MsgTypes::Draw`. `clear` resets the canvas instead of
+/// replaying `points`, e.g. when a user presses the clear button.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub(crate) struct DrawBatch {
+    #[serde(default)]
+    pub(crate) points: Vec<StrokePoint>,
+    #[serde(default)]
+    pub(crate) clear: bool,
+    /// Opaque per-session id set by the originating `Canvas`, so a client
+    /// can recognize and skip replaying its own strokes when the server
+    /// echoes them back through the shared `EventBus`.
+    #[serde(default)]
+    pub(crate) sender: String,
+}
+
+/// Payload for an outgoing `MsgTypes::Message`: the server stamps `from`,
+/// `id`, and `timestamp` before broadcasting it back as a `MessageData`.
+#[derive(Debug, Serialize)]
+struct OutgoingMessage {
+    room: RoomId,
+    message: String,
+}
+
+/// Request payload for `MsgTypes::History`: the id/timestamp of the last
+/// message this client already has, so the server only replays the delta.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct HistoryRequest {
+    room: RoomId,
+    since_id: Option<String>,
+    since_timestamp: Option<f64>,
 }
 
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct WebSocketMessage {
-    message_type: MsgTypes,
-    data_array: Option<Vec<String>>,
-    data: Option<String>,
+pub(crate) struct WebSocketMessage {
+    pub(crate) message_type: MsgTypes,
+    pub(crate) data_array: Option<Vec<String>>,
+    pub(crate) data: Option<String>,
+    /// Set by a server-side bridge when this envelope relays a message from
+    /// another platform, so the client can attribute it even before parsing
+    /// `data` into a `MessageData`.
+    #[serde(default)]
+    pub(crate) origin: Option<Origin>,
 }
 
 #[derive(Clone)]
 struct UserProfile {
     name: String,
     avatar: String,
+    status: PresenceState,
+    last_typing: Option<f64>,
+}
+
+/// A user is shown as "typing…" while a typing event for them arrived more
+/// recently than this window; after that we assume it went stale (e.g. they
+/// closed the tab without sending a stopped-typing event).
+const TYPING_INDICATOR_TIMEOUT_MS: f64 = 4000.0;
+
+impl UserProfile {
+    fn is_typing(&self) -> bool {
+        match self.last_typing {
+            Some(since) => js_sys::Date::now() - since < TYPING_INDICATOR_TIMEOUT_MS,
+            None => false,
+        }
+    }
+
+    fn status_dot_class(&self) -> &'static str {
+        match self.status {
+            PresenceState::Online => "bg-green-500",
+            PresenceState::Away => "bg-yellow-500",
+            PresenceState::Offline => "bg-gray-400",
+        }
+    }
+}
+
+const DEFAULT_ROOM: &str = "lobby";
+
+fn dicebear_avatar(seed: &str) -> String {
+    format!("https://api.dicebear.com/9.x/notionists-neutral/svg?seed={seed}")
+}
+
+/// Schemes we're willing to render as a clickable `<a href>`. Anything else
+/// (`javascript:`, `data:`, etc.) is rendered as plain text instead, since a
+/// message body is attacker-controlled and `javascript:` URIs execute in the
+/// clicking user's session same-origin.
+const ALLOWED_LINK_SCHEMES: &[&str] = &["http://", "https://", "mailto:"];
+
+fn is_safe_link(url: &str) -> bool {
+    let lower = url.trim().to_ascii_lowercase();
+    ALLOWED_LINK_SCHEMES
+        .iter()
+        .any(|scheme| lower.starts_with(scheme))
+}
+
+/// Renders a chat message body as Markdown, mapping each `pulldown-cmark`
+/// event directly to Yew `Html` nodes. We never build raw HTML strings here
+/// (e.g. via `Html::from_html_unchecked`), so there's no injection risk even
+/// for messages containing `<script>`-like text.
+fn render_message(message: &str) -> Html {
+    let mut stack: Vec<(Tag, Vec<Html>)> = vec![];
+    let mut root: Vec<Html> = vec![];
+
+    for event in Parser::new(message) {
+        match event {
+            Event::Start(tag) => stack.push((tag, vec![])),
+            Event::End(_) => {
+                if let Some((tag, children)) = stack.pop() {
+                    push_node(&mut stack, &mut root, wrap_markdown_tag(tag, children));
+                }
+            }
+            Event::Text(text) => push_node(&mut stack, &mut root, html! { {text.to_string()} }),
+            Event::Code(code) => push_node(
+                &mut stack,
+                &mut root,
+                html! { <code class="font-mono text-xs bg-black/20 rounded px-1">{code.to_string()}</code> },
+            ),
+            Event::SoftBreak | Event::HardBreak => {
+                push_node(&mut stack, &mut root, html! { <br/> })
+            }
+            _ => {}
+        }
+    }
+
+    html! { <>{ for root }</> }
+}
+
+fn push_node(stack: &mut Vec<(Tag, Vec<Html>)>, root: &mut Vec<Html>, node: Html) {
+    match stack.last_mut() {
+        Some((_, children)) => children.push(node),
+        None => root.push(node),
+    }
+}
+
+fn wrap_markdown_tag(tag: Tag, children: Vec<Html>) -> Html {
+    match tag {
+        Tag::Paragraph => html! { <p class="mb-1">{ for children }</p> },
+        Tag::Emphasis => html! { <em>{ for children }</em> },
+        Tag::Strong => html! { <strong>{ for children }</strong> },
+        Tag::Strikethrough => html! { <del>{ for children }</del> },
+        Tag::BlockQuote => {
+            html! { <blockquote class="border-l-2 pl-2 italic opacity-80">{ for children }</blockquote> }
+        }
+        Tag::List(Some(_)) => html! { <ol class="list-decimal ml-5">{ for children }</ol> },
+        Tag::List(None) => html! { <ul class="list-disc ml-5">{ for children }</ul> },
+        Tag::Item => html! { <li>{ for children }</li> },
+        Tag::CodeBlock(_) => {
+            html! { <pre class="font-mono text-xs bg-black/20 rounded p-2 my-1 overflow-x-auto"><code>{ for children }</code></pre> }
+        }
+        Tag::Link(_, url, _) => {
+            if is_safe_link(&url) {
+                html! { <a href={url.to_string()} target="_blank" rel="noopener noreferrer" class="underline">{ for children }</a> }
+            } else {
+                html! { <span class="underline decoration-dotted">{ for children }</span> }
+            }
+        }
+        Tag::Heading(..) => html! { <strong class="block">{ for children }</strong> },
+        _ => html! { <>{ for children }</> },
+    }
 }
 
 pub struct Chat {
+    username: String,
     users: Vec<UserProfile>,
     chat_input: NodeRef,
     wss: WebsocketService,
-    messages: Vec<MessageData>,
+    rooms: Vec<Room>,
+    current_room: RoomId,
+    room_messages: HashMap<RoomId, Vec<MessageData>>,
+    histories: HashMap<RoomId, HistoryStore>,
     _producer: Box<dyn Bridge<EventBus>>,
     dark_mode: bool,
+    is_typing: bool,
+    typing_timeout: Option<Timeout>,
+    call_state: CallState,
+    call: Option<Rc<CallService>>,
+    local_stream: Option<MediaStream>,
+    remote_stream: Option<MediaStream>,
+    local_video: NodeRef,
+    remote_video: NodeRef,
+}
+
+impl Chat {
+    fn send_typing(&self, typing: bool) {
+        let payload = TypingPayload {
+            user: self.username.clone(),
+            typing,
+        };
+        let message = WebSocketMessage {
+            message_type: MsgTypes::Typing,
+            data: Some(serde_json::to_string(&payload).unwrap()),
+            data_array: None,
+            origin: None,
+        };
+        if let Err(e) = self
+            .wss
+            .tx
+            .clone()
+            .try_send(serde_json::to_string(&message).unwrap())
+        {
+            log::debug!("error sending typing event: {:?}", e);
+        }
+    }
+
+    fn send_room_event(&self, message_type: MsgTypes, room: &RoomId) {
+        let message = WebSocketMessage {
+            message_type,
+            data: Some(room.clone()),
+            data_array: None,
+            origin: None,
+        };
+        if let Err(e) = self
+            .wss
+            .tx
+            .clone()
+            .try_send(serde_json::to_string(&message).unwrap())
+        {
+            log::debug!("error sending room event: {:?}", e);
+        }
+    }
+
+    fn current_messages(&self) -> &[MessageData] {
+        self.room_messages
+            .get(&self.current_room)
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    /// Looks up the sender's profile, generating one on the fly for bridged
+    /// or otherwise unrecognized senders instead of panicking.
+    fn resolve_user(&self, name: &str) -> UserProfile {
+        self.users
+            .iter()
+            .find(|u| u.name == name)
+            .cloned()
+            .unwrap_or_else(|| UserProfile {
+                name: name.to_string(),
+                avatar: dicebear_avatar(name),
+                status: PresenceState::Offline,
+                last_typing: None,
+            })
+    }
+
+    fn send_draw(&self, batch: DrawBatch) {
+        let message = WebSocketMessage {
+            message_type: MsgTypes::Draw,
+            data: Some(serde_json::to_string(&batch).unwrap()),
+            data_array: None,
+            origin: None,
+        };
+        if let Err(e) = self
+            .wss
+            .tx
+            .clone()
+            .try_send(serde_json::to_string(&message).unwrap())
+        {
+            log::debug!("error sending draw batch: {:?}", e);
+        }
+    }
+
+    fn history_for(&mut self, room: &RoomId) -> &HistoryStore {
+        self.histories
+            .entry(room.clone())
+            .or_insert_with(|| HistoryStore::new(room))
+    }
+
+    /// Sends a room-scoped `MsgTypes::History` request carrying the id/
+    /// timestamp of the last message already known for `room`, so the
+    /// server only replays the delta.
+    fn request_history(&mut self, room: &RoomId) {
+        let last_known = self.history_for(room).last_message();
+        let history_request = HistoryRequest {
+            room: room.clone(),
+            since_id: last_known.as_ref().and_then(|m| m.id.clone()),
+            since_timestamp: last_known.as_ref().and_then(|m| m.timestamp),
+        };
+        let message = WebSocketMessage {
+            message_type: MsgTypes::History,
+            data: Some(serde_json::to_string(&history_request).unwrap()),
+            data_array: None,
+            origin: None,
+        };
+        if let Err(e) = self
+            .wss
+            .tx
+            .clone()
+            .try_send(serde_json::to_string(&message).unwrap())
+        {
+            log::debug!("error requesting history: {:?}", e);
+        }
+    }
+
+    fn send_signal(
+        &self,
+        message_type: MsgTypes,
+        to: &str,
+        sdp: Option<String>,
+        candidate: Option<String>,
+    ) {
+        self.send_signal_with_ice(message_type, to, sdp, candidate, None, None);
+    }
+
+    fn send_signal_with_ice(
+        &self,
+        message_type: MsgTypes,
+        to: &str,
+        sdp: Option<String>,
+        candidate: Option<String>,
+        sdp_mid: Option<String>,
+        sdp_m_line_index: Option<u16>,
+    ) {
+        let signal = CallSignal {
+            from: self.username.clone(),
+            to: to.to_string(),
+            sdp,
+            candidate,
+            sdp_mid,
+            sdp_m_line_index,
+        };
+        let message = WebSocketMessage {
+            message_type,
+            data: Some(serde_json::to_string(&signal).unwrap()),
+            data_array: None,
+            origin: None,
+        };
+        if let Err(e) = self
+            .wss
+            .tx
+            .clone()
+            .try_send(serde_json::to_string(&message).unwrap())
+        {
+            log::debug!("error sending call signal: {:?}", e);
+        }
+    }
+
+    /// Wires a fresh `CallService` for a new call, forwarding ICE
+    /// candidates and the remote track back into the component as messages.
+    fn begin_call(&self, ctx: &Context<Self>) -> Rc<CallService> {
+        let link = ctx.link().clone();
+        let link2 = ctx.link().clone();
+        let call = CallService::new(
+            move |candidate, sdp_mid, sdp_m_line_index| {
+                link.send_message(Msg::LocalIceCandidate(candidate, sdp_mid, sdp_m_line_index))
+            },
+            move |stream| link2.send_message(Msg::RemoteStreamReady(stream)),
+        )
+        .expect("failed to create RTCPeerConnection");
+        Rc::new(call)
+    }
 }
 
 impl Component for Chat {
@@ -58,60 +524,421 @@ impl Component for Chat {
             .link()
             .context::<User>(Callback::noop())
             .expect("context to be set");
-        let wss = WebsocketService::new();
+        // Registration, the initial history replay request, and joining the
+        // lobby are all (re-)sent from `Msg::Reconnected`, which this fires
+        // for us once the handshake completes — including on the very first
+        // connection, so there's no separate one-off send here.
+        let wss = WebsocketService::new(ctx.link().callback(|_| Msg::Reconnected));
         let username = user.username.borrow().clone();
 
-        let message = WebSocketMessage {
-            message_type: MsgTypes::Register,
-            data: Some(username.to_string()),
-            data_array: None,
-        };
+        let default_room: RoomId = DEFAULT_ROOM.to_string();
+        let history = HistoryStore::new(&default_room);
+        let messages = history.load();
 
-        if let Ok(_) = wss
-            .tx
-            .clone()
-            .try_send(serde_json::to_string(&message).unwrap())
-        {
-            log::debug!("message sent successfully");
-        }
+        let rooms = KNOWN_ROOMS
+            .iter()
+            .map(|(id, name)| Room {
+                id: id.to_string(),
+                name: name.to_string(),
+                unread: 0,
+            })
+            .collect();
 
         Self {
+            username,
             users: vec![],
-            messages: vec![],
+            rooms,
+            current_room: default_room.clone(),
+            room_messages: HashMap::from([(default_room.clone(), messages)]),
+            histories: HashMap::from([(default_room, history)]),
             chat_input: NodeRef::default(),
             wss,
             _producer: EventBus::bridge(ctx.link().callback(Msg::HandleMsg)),
             dark_mode: false,
+            is_typing: false,
+            typing_timeout: None,
+            call_state: CallState::Idle,
+            call: None,
+            local_stream: None,
+            remote_stream: None,
+            local_video: NodeRef::default(),
+            remote_video: NodeRef::default(),
         }
    }
 
-    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
             Msg::ToggleDarkMode => {
                 self.dark_mode = !self.dark_mode;
                 true // Re-render the component
             }
+            Msg::Typing => {
+                if !self.is_typing {
+                    self.is_typing = true;
+                    self.send_typing(true);
+                }
+                let link = ctx.link().clone();
+                self.typing_timeout = Some(Timeout::new(TYPING_DEBOUNCE_MS, move || {
+                    link.send_message(Msg::StoppedTyping);
+                }));
+                false
+            }
+            Msg::StoppedTyping => {
+                self.is_typing = false;
+                self.typing_timeout = None;
+                self.send_typing(false);
+                false
+            }
+            Msg::Reconnected => {
+                let message = WebSocketMessage {
+                    message_type: MsgTypes::Register,
+                    data: Some(self.username.clone()),
+                    data_array: None,
+                    origin: None,
+                };
+                if let Err(e) = self
+                    .wss
+                    .tx
+                    .clone()
+                    .try_send(serde_json::to_string(&message).unwrap())
+                {
+                    log::debug!("error sending register: {:?}", e);
+                }
+
+                let room = self.current_room.clone();
+                self.request_history(&room);
+                self.send_room_event(MsgTypes::JoinRoom, &room);
+                false
+            }
+            Msg::SwitchRoom(room) => {
+                if room == self.current_room {
+                    return false;
+                }
+                self.send_room_event(MsgTypes::LeaveRoom, &self.current_room.clone());
+                self.send_room_event(MsgTypes::JoinRoom, &room);
+
+                if !self.room_messages.contains_key(&room) {
+                    // First visit to this room this session: hydrate from
+                    // whatever's cached locally and ask the server for
+                    // anything newer, same as the lobby gets at startup
+                    // (create() plus Msg::Reconnected).
+                    let cached = self.history_for(&room).load();
+                    self.room_messages.insert(room.clone(), cached);
+                    self.request_history(&room);
+                }
+
+                self.current_room = room.clone();
+                if let Some(r) = self.rooms.iter_mut().find(|r| r.id == room) {
+                    r.unread = 0;
+                }
+                true
+            }
+            Msg::SendDraw(batch) => {
+                self.send_draw(batch);
+                false
+            }
+            Msg::StartCall(with) => {
+                let call = self.begin_call(ctx);
+                self.call = Some(call.clone());
+                self.call_state = CallState::Outgoing { with: with.clone() };
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let stream = match call.attach_local_stream().await {
+                        Ok(stream) => stream,
+                        Err(e) => {
+                            log::error!("failed to get local media: {:?}", e);
+                            return;
+                        }
+                    };
+                    link.send_message(Msg::LocalStreamReady(stream));
+                    match call.create_offer().await {
+                        Ok(sdp) => link.send_message(Msg::SendCallOffer(with, sdp)),
+                        Err(e) => log::error!("failed to create offer: {:?}", e),
+                    }
+                });
+                true
+            }
+            Msg::AcceptCall => {
+                let CallState::Incoming { with, offer_sdp } = self.call_state.clone() else {
+                    return false;
+                };
+                let call = self.begin_call(ctx);
+                self.call = Some(call.clone());
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let stream = match call.attach_local_stream().await {
+                        Ok(stream) => stream,
+                        Err(e) => {
+                            log::error!("failed to get local media: {:?}", e);
+                            return;
+                        }
+                    };
+                    link.send_message(Msg::LocalStreamReady(stream));
+                    match call.create_answer(&offer_sdp).await {
+                        Ok(sdp) => link.send_message(Msg::SendCallAnswer(with, sdp)),
+                        Err(e) => log::error!("failed to create answer: {:?}", e),
+                    }
+                });
+                true
+            }
+            Msg::DeclineCall => {
+                if let CallState::Incoming { with, .. } = &self.call_state {
+                    self.send_signal(MsgTypes::CallEnd, &with.clone(), None, None);
+                }
+                self.call_state = CallState::Idle;
+                true
+            }
+            Msg::HangUp => {
+                let with = match &self.call_state {
+                    CallState::Outgoing { with }
+                    | CallState::Incoming { with, .. }
+                    | CallState::Connected { with } => Some(with.clone()),
+                    CallState::Idle => None,
+                };
+                if let Some(with) = with {
+                    self.send_signal(MsgTypes::CallEnd, &with, None, None);
+                }
+                if let Some(call) = self.call.take() {
+                    call.hang_up();
+                }
+                self.call_state = CallState::Idle;
+                self.local_stream = None;
+                self.remote_stream = None;
+                true
+            }
+            Msg::LocalStreamReady(stream) => {
+                self.local_stream = Some(stream);
+                true
+            }
+            Msg::RemoteStreamReady(stream) => {
+                self.remote_stream = Some(stream);
+                true
+            }
+            Msg::LocalIceCandidate(candidate, sdp_mid, sdp_m_line_index) => {
+                let with = match &self.call_state {
+                    CallState::Outgoing { with }
+                    | CallState::Incoming { with, .. }
+                    | CallState::Connected { with } => with.clone(),
+                    CallState::Idle => return false,
+                };
+                self.send_signal_with_ice(
+                    MsgTypes::IceCandidate,
+                    &with,
+                    None,
+                    Some(candidate),
+                    sdp_mid,
+                    sdp_m_line_index,
+                );
+                false
+            }
+            Msg::SendCallOffer(to, sdp) => {
+                self.send_signal(MsgTypes::CallOffer, &to, Some(sdp), None);
+                false
+            }
+            Msg::SendCallAnswer(to, sdp) => {
+                self.call_state = CallState::Connected { with: to.clone() };
+                self.send_signal(MsgTypes::CallAnswer, &to, Some(sdp), None);
+                true
+            }
+            Msg::CallConnected => {
+                if let CallState::Outgoing { with } = self.call_state.clone() {
+                    self.call_state = CallState::Connected { with };
+                }
+                true
+            }
             Msg::HandleMsg(s) => {
-                let msg: WebSocketMessage = serde_json::from_str(&s).unwrap();
+                let Ok(msg) = serde_json::from_str::<WebSocketMessage>(&s) else {
+                    log::error!("dropping malformed websocket frame: {s}");
+                    return false;
+                };
                 match msg.message_type {
                     MsgTypes::Users => {
                         let users_from_message = msg.data_array.unwrap_or_default();
+                        let previous = std::mem::take(&mut self.users);
                         self.users = users_from_message
                             .iter()
-                            .map(|u| UserProfile {
-                                name: u.into(),
-                                avatar: format!(
-                                    "https://api.dicebear.com/9.x/notionists-neutral/svg"
-                                )
-                                .into(),
+                            .map(|name| {
+                                // Preserve presence/typing state for users who
+                                // were already known; a Users broadcast just
+                                // reflects roster membership, not presence.
+                                previous
+                                    .iter()
+                                    .find(|u| &u.name == name)
+                                    .cloned()
+                                    .unwrap_or_else(|| UserProfile {
+                                        name: name.into(),
+                                        avatar: format!(
+                                            "https://api.dicebear.com/9.x/notionists-neutral/svg"
+                                        )
+                                        .into(),
+                                        status: PresenceState::Online,
+                                        last_typing: None,
+                                    })
                             })
                             .collect();
                         return true;
                     }
                     MsgTypes::Message => {
-                        let message_data: MessageData =
-                            serde_json::from_str(&msg.data.unwrap()).unwrap();
-                        self.messages.push(message_data);
+                        let Ok(mut message_data) =
+                            serde_json::from_str::<MessageData>(&msg.data.unwrap_or_default())
+                        else {
+                            log::error!("dropping malformed message frame");
+                            return false;
+                        };
+                        // A server-side bridge may stamp the envelope's
+                        // origin rather than embedding it in the message
+                        // body; honor it as a fallback so messages relayed
+                        // that way still get attributed.
+                        if message_data.origin.is_none() {
+                            message_data.origin = msg.origin.clone();
+                        }
+                        let room = message_data
+                            .room
+                            .clone()
+                            .unwrap_or_else(|| DEFAULT_ROOM.to_string());
+                        self.history_for(&room).append(&message_data);
+                        self.room_messages
+                            .entry(room.clone())
+                            .or_default()
+                            .push(message_data);
+                        if room != self.current_room {
+                            if let Some(r) = self.rooms.iter_mut().find(|r| r.id == room) {
+                                r.unread += 1;
+                            }
+                        }
+                        return true;
+                    }
+                    MsgTypes::History => {
+                        let replayed: Vec<MessageData> =
+                            serde_json::from_str(&msg.data.unwrap_or_default()).unwrap_or_default();
+                        for message in &replayed {
+                            let room = message
+                                .room
+                                .clone()
+                                .unwrap_or_else(|| DEFAULT_ROOM.to_string());
+                            self.history_for(&room).append(message);
+                            self.room_messages
+                                .entry(room)
+                                .or_default()
+                                .push(message.clone());
+                        }
+                        return true;
+                    }
+                    MsgTypes::Presence => {
+                        let Ok(presence) =
+                            serde_json::from_str::<PresencePayload>(&msg.data.unwrap_or_default())
+                        else {
+                            return false;
+                        };
+                        if let Some(user) = self.users.iter_mut().find(|u| u.name == presence.user)
+                        {
+                            user.status = presence.status;
+                        }
+                        return true;
+                    }
+                    MsgTypes::Typing => {
+                        let Ok(typing) =
+                            serde_json::from_str::<TypingPayload>(&msg.data.unwrap_or_default())
+                        else {
+                            return false;
+                        };
+                        if let Some(user) = self.users.iter_mut().find(|u| u.name == typing.user) {
+                            user.last_typing = typing.typing.then(js_sys::Date::now);
+                        }
+                        return true;
+                    }
+                    MsgTypes::CallOffer => {
+                        let Ok(signal) =
+                            serde_json::from_str::<CallSignal>(&msg.data.unwrap_or_default())
+                        else {
+                            return false;
+                        };
+                        if signal.to != self.username {
+                            return false;
+                        }
+                        if self.call_state != CallState::Idle {
+                            // Already on a call (or mid-setup); tell the
+                            // caller we're busy instead of leaving them
+                            // stuck on "Calling…" with no response.
+                            self.send_signal(MsgTypes::CallEnd, &signal.from, None, None);
+                            return false;
+                        }
+                        let Some(offer_sdp) = signal.sdp else {
+                            return false;
+                        };
+                        self.call_state = CallState::Incoming {
+                            with: signal.from,
+                            offer_sdp,
+                        };
+                        return true;
+                    }
+                    MsgTypes::CallAnswer => {
+                        let Ok(signal) =
+                            serde_json::from_str::<CallSignal>(&msg.data.unwrap_or_default())
+                        else {
+                            return false;
+                        };
+                        if signal.to != self.username {
+                            return false;
+                        }
+                        let Some(answer_sdp) = signal.sdp else {
+                            return false;
+                        };
+                        if let Some(call) = self.call.clone() {
+                            let link = ctx.link().clone();
+                            spawn_local(async move {
+                                if let Err(e) = call.accept_answer(&answer_sdp).await {
+                                    log::error!("failed to accept answer: {:?}", e);
+                                    return;
+                                }
+                                link.send_message(Msg::CallConnected);
+                            });
+                        }
+                        return false;
+                    }
+                    MsgTypes::IceCandidate => {
+                        let Ok(signal) =
+                            serde_json::from_str::<CallSignal>(&msg.data.unwrap_or_default())
+                        else {
+                            return false;
+                        };
+                        if signal.to != self.username {
+                            return false;
+                        }
+                        let Some(candidate) = signal.candidate else {
+                            return false;
+                        };
+                        if let Some(call) = self.call.clone() {
+                            spawn_local(async move {
+                                if let Err(e) = call
+                                    .add_ice_candidate(
+                                        &candidate,
+                                        signal.sdp_mid,
+                                        signal.sdp_m_line_index,
+                                    )
+                                    .await
+                                {
+                                    log::error!("failed to add ice candidate: {:?}", e);
+                                }
+                            });
+                        }
+                        return false;
+                    }
+                    MsgTypes::CallEnd => {
+                        let Ok(signal) =
+                            serde_json::from_str::<CallSignal>(&msg.data.unwrap_or_default())
+                        else {
+                            return false;
+                        };
+                        if signal.to != self.username {
+                            return false;
+                        }
+                        if let Some(call) = self.call.take() {
+                            call.hang_up();
+                        }
+                        self.call_state = CallState::Idle;
+                        self.local_stream = None;
+                        self.remote_stream = None;
                         return true;
                     }
                     _ => {
@@ -123,10 +950,15 @@ impl Component for Chat {
                 let input = self.chat_input.cast::<HtmlInputElement>();
                 if let Some(input) = input {
                     //log::debug!("got input: {:?}", input.value());
+                    let outgoing = OutgoingMessage {
+                        room: self.current_room.clone(),
+                        message: input.value(),
+                    };
                     let message = WebSocketMessage {
                         message_type: MsgTypes::Message,
-                        data: Some(input.value()),
+                        data: Some(serde_json::to_string(&outgoing).unwrap()),
                         data_array: None,
+                        origin: None,
                     };
                     if let Err(e) = self
                         .wss
@@ -146,6 +978,7 @@ impl Component for Chat {
     fn view(&self, ctx: &Context<Self>) -> Html {
         let submit = ctx.link().callback(|_| Msg::SubmitMessage);
         let toggle_dark_mode = ctx.link().callback(|_| Msg::ToggleDarkMode);
+        let on_input = ctx.link().callback(|_| Msg::Typing);
         
         // Define theme classes based on dark mode state
         let (bg_primary, bg_secondary, bg_tertiary, text_primary, text_secondary, border_color) = if self.dark_mode {
@@ -156,6 +989,32 @@ impl Component for Chat {
 
         html! {
             <div class={format!("flex w-screen {}", if self.dark_mode { "bg-gray-900" } else { "bg-white" })}>
+                // Rooms column
+                <div class={format!("flex-none w-40 h-screen border-r-2 {} {}", border_color, bg_tertiary)}>
+                    <div class={format!("text-xl p-3 {}", text_primary)}>{"Rooms"}</div>
+                    {
+                        self.rooms.iter().map(|r| {
+                            let is_active = r.id == self.current_room;
+                            let room_id = r.id.clone();
+                            let switch = ctx.link().callback(move |_| Msg::SwitchRoom(room_id.clone()));
+                            html!{
+                                <div
+                                    onclick={switch}
+                                    class={format!(
+                                        "flex justify-between items-center m-2 p-2 rounded-lg cursor-pointer {} {}",
+                                        if is_active { "bg-blue-600 text-white" } else { text_primary },
+                                        bg_secondary
+                                    )}
+                                >
+                                    <span>{r.name.clone()}</span>
+                                    if r.unread > 0 {
+                                        <span class="bg-red-500 text-white text-xs rounded-full px-2">{r.unread.to_string()}</span>
+                                    }
+                                </div>
+                            }
+                        }).collect::<Html>()
+                    }
+                </div>
                 // Sidebar
                 <div class={format!("flex-none w-56 h-screen {}", bg_secondary)}>
                     // Header with dark mode toggle
@@ -179,49 +1038,114 @@ impl Component for Chat {
                     // Users list
                     {
                         self.users.clone().iter().map(|u| {
+                            let is_self = u.name == self.username;
+                            let call_target = u.name.clone();
+                            let start_call = ctx.link().callback(move |_| Msg::StartCall(call_target.clone()));
                             html!{
                                 <div class={format!("flex m-3 {} rounded-lg p-2 transition-colors", bg_tertiary)}>
-                                    <div>
+                                    <div class="relative">
                                         <img class="w-12 h-12 rounded-full" src={u.avatar.clone()} alt="avatar"/>
+                                        <span class={format!("absolute bottom-0 right-0 w-3 h-3 rounded-full border-2 border-white {}", u.status_dot_class())}></span>
                                     </div>
                                     <div class="flex-grow p-3">
                                         <div class={format!("flex text-xs justify-between {}", text_primary)}>
                                             <div>{u.name.clone()}</div>
                                         </div>
                                         <div class="text-xs text-gray-400">
-                                            {"Hi there!"}
+                                            if u.is_typing() {
+                                                <span class="italic animate-pulse">{"typing…"}</span>
+                                            } else {
+                                                {"Hi there!"}
+                                            }
                                         </div>
                                     </div>
+                                    if !is_self && self.call_state == CallState::Idle {
+                                        <button
+                                            onclick={start_call}
+                                            title="Start call"
+                                            class="self-center p-2 rounded-full hover:bg-green-200 transition-colors"
+                                        >
+                                            {"📞"}
+                                        </button>
+                                    }
                                 </div>
                             }
                         }).collect::<Html>()
                     }
                 </div>
-                
+
                 // Main chat area
                 <div class={format!("grow h-screen flex flex-col {}", bg_primary)}>
                     // Chat header
-                    <div class={format!("w-full h-14 border-b-2 {}", border_color)}>
-                        <div class={format!("text-xl p-3 {}", text_primary)}>{"💬 Chat!"}</div>
+                    <div class={format!("w-full border-b-2 {}", border_color)}>
+                        <div class={format!("flex justify-between items-center h-14 text-xl p-3 {}", text_primary)}>
+                            <span>{"💬 Chat!"}</span>
+                        </div>
+                        {
+                            match &self.call_state {
+                                CallState::Idle => html! {},
+                                CallState::Outgoing { with } => {
+                                    let hang_up = ctx.link().callback(|_| Msg::HangUp);
+                                    html! {
+                                        <div class="flex justify-between items-center px-3 pb-2 text-sm">
+                                            <span>{format!("Calling {}…", with)}</span>
+                                            <button onclick={hang_up} class="px-3 py-1 rounded-full bg-red-600 text-white">{"Hang up"}</button>
+                                        </div>
+                                    }
+                                }
+                                CallState::Incoming { with, .. } => {
+                                    let accept = ctx.link().callback(|_| Msg::AcceptCall);
+                                    let decline = ctx.link().callback(|_| Msg::DeclineCall);
+                                    html! {
+                                        <div class="flex justify-between items-center px-3 pb-2 text-sm">
+                                            <span>{format!("{} is calling…", with)}</span>
+                                            <span>
+                                                <button onclick={accept} class="px-3 py-1 mr-2 rounded-full bg-green-600 text-white">{"Accept"}</button>
+                                                <button onclick={decline} class="px-3 py-1 rounded-full bg-red-600 text-white">{"Decline"}</button>
+                                            </span>
+                                        </div>
+                                    }
+                                }
+                                CallState::Connected { with } => {
+                                    let hang_up = ctx.link().callback(|_| Msg::HangUp);
+                                    html! {
+                                        <div class="px-3 pb-2 text-sm">
+                                            <div class="flex justify-between items-center">
+                                                <span>{format!("On call with {}", with)}</span>
+                                                <button onclick={hang_up} class="px-3 py-1 rounded-full bg-red-600 text-white">{"Hang up"}</button>
+                                            </div>
+                                            <div class="flex gap-2 mt-2">
+                                                <video ref={self.local_video.clone()} autoplay=true muted=true class="w-32 h-24 bg-black rounded"></video>
+                                                <video ref={self.remote_video.clone()} autoplay=true class="w-32 h-24 bg-black rounded"></video>
+                                            </div>
+                                        </div>
+                                    }
+                                }
+                            }
+                        }
                     </div>
-                    
+
                     // Messages area
                     <div class={format!("w-full grow overflow-auto border-b-2 {}", border_color)}>
                         {
-                            self.messages.iter().map(|m| {
-                                let user = self.users.iter().find(|u| u.name == m.from).unwrap();
+                            self.current_messages().iter().map(|m| {
+                                let user = self.resolve_user(&m.from);
+                                let display_name = m.origin.as_ref().map_or(m.from.clone(), |o| o.author.clone());
                                 html!{
                                     <div class={format!("flex items-end w-3/6 {} m-8 rounded-tl-lg rounded-tr-lg rounded-br-lg transition-colors", bg_secondary)}>
                                         <img class="w-8 h-8 rounded-full m-3" src={user.avatar.clone()} alt="avatar"/>
                                         <div class="p-3">
-                                            <div class={format!("text-sm {}", text_primary)}>
-                                                {m.from.clone()}
+                                            <div class={format!("flex items-center gap-1 text-sm {}", text_primary)}>
+                                                <span>{display_name}</span>
+                                                if let Some(origin) = &m.origin {
+                                                    <span class="text-[10px] px-1 rounded bg-indigo-500 text-white uppercase">{origin.platform.clone()}</span>
+                                                }
                                             </div>
                                             <div class={format!("text-xs {}", text_secondary)}>
                                                 if m.message.ends_with(".gif") {
                                                     <img class="mt-3" src={m.message.clone()}/>
                                                 } else {
-                                                    {m.message.clone()}
+                                                    {render_message(&m.message)}
                                                 }
                                             </div>
                                         </div>
@@ -233,10 +1157,11 @@ impl Component for Chat {
                     
                     // Input area
                     <div class="w-full h-14 flex px-3 items-center">
-                        <input 
-                            ref={self.chat_input.clone()} 
-                            type="text" 
-                            placeholder="Message" 
+                        <input
+                            ref={self.chat_input.clone()}
+                            oninput={on_input}
+                            type="text"
+                            placeholder="Message"
                             class={format!("block w-full py-2 pl-4 mx-3 {} rounded-full outline-none focus:{} transition-colors", 
                                 bg_secondary, 
                                 text_primary
@@ -255,7 +1180,22 @@ impl Component for Chat {
                         </button>
                     </div>
                 </div>
+
+                // Shared sketch canvas
+                <div class={format!("flex-none w-[504px] h-screen border-l-2 {}", border_color)}>
+                    <div class={format!("text-xl p-3 {}", text_primary)}>{"🎨 Sketch"}</div>
+                    <Canvas on_draw={ctx.link().callback(Msg::SendDraw)} />
+                </div>
             </div>
         }
     }
+
+    fn rendered(&mut self, _ctx: &Context<Self>, _first_render: bool) {
+        if let Some(video) = self.local_video.cast::<HtmlVideoElement>() {
+            video.set_src_object(self.local_stream.as_ref());
+        }
+        if let Some(video) = self.remote_video.cast::<HtmlVideoElement>() {
+            video.set_src_object(self.remote_stream.as_ref());
+        }
+    }
 }
\ No newline at end of file
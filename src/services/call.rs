@@ -0,0 +1,133 @@
+use std::cell::RefCell;
+
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    MediaStream, MediaStreamConstraints, MediaStreamTrack, RtcIceCandidateInit, RtcPeerConnection,
+    RtcPeerConnectionIceEvent, RtcSdpType, RtcSessionDescriptionInit, RtcTrackEvent,
+};
+
+/// Thin wrapper around `RtcPeerConnection` that surfaces ICE candidates and
+/// remote tracks through plain closures, so `Chat` never has to touch
+/// `web_sys` WebRTC types directly.
+pub struct CallService {
+    connection: RtcPeerConnection,
+    /// The local camera/mic stream acquired by `attach_local_stream`, kept
+    /// around so `hang_up` can stop every track — `RtcPeerConnection::close`
+    /// alone doesn't release the capture devices, so without this the
+    /// browser's camera/mic indicator stays on after the call ends.
+    local_stream: RefCell<Option<MediaStream>>,
+}
+
+impl CallService {
+    pub fn new(
+        on_ice_candidate: impl Fn(String, Option<String>, Option<u16>) + 'static,
+        on_remote_stream: impl Fn(MediaStream) + 'static,
+    ) -> Result<Self, JsValue> {
+        let connection = RtcPeerConnection::new()?;
+
+        let ice_callback = Closure::<dyn Fn(RtcPeerConnectionIceEvent)>::new(
+            move |event: RtcPeerConnectionIceEvent| {
+                if let Some(candidate) = event.candidate() {
+                    on_ice_candidate(
+                        candidate.candidate(),
+                        candidate.sdp_mid(),
+                        candidate.sdp_m_line_index(),
+                    );
+                }
+            },
+        );
+        connection.set_onicecandidate(Some(ice_callback.as_ref().unchecked_ref()));
+        ice_callback.forget();
+
+        let track_callback =
+            Closure::<dyn Fn(RtcTrackEvent)>::new(move |event: RtcTrackEvent| {
+                if let Some(stream) = event.streams().get(0).dyn_into::<MediaStream>().ok() {
+                    on_remote_stream(stream);
+                }
+            });
+        connection.set_ontrack(Some(track_callback.as_ref().unchecked_ref()));
+        track_callback.forget();
+
+        Ok(Self {
+            connection,
+            local_stream: RefCell::new(None),
+        })
+    }
+
+    /// Requests the local camera/mic and attaches every track to the peer
+    /// connection, returning the stream so the caller can preview it.
+    pub async fn attach_local_stream(&self) -> Result<MediaStream, JsValue> {
+        let window = web_sys::window().ok_or_else(|| JsValue::from_str("no window"))?;
+        let media_devices = window.navigator().media_devices()?;
+        let mut constraints = MediaStreamConstraints::new();
+        constraints.audio(&JsValue::TRUE);
+        constraints.video(&JsValue::TRUE);
+        let promise = media_devices.get_user_media_with_constraints(&constraints)?;
+        let stream: MediaStream = JsFuture::from(promise).await?.unchecked_into();
+        for track in stream.get_tracks().iter() {
+            self.connection
+                .add_track_0(&track.unchecked_into(), &stream);
+        }
+        *self.local_stream.borrow_mut() = Some(stream.clone());
+        Ok(stream)
+    }
+
+    pub async fn create_offer(&self) -> Result<String, JsValue> {
+        let offer: RtcSessionDescriptionInit =
+            JsFuture::from(self.connection.create_offer()).await?.unchecked_into();
+        JsFuture::from(self.connection.set_local_description(&offer)).await?;
+        sdp_of(&offer)
+    }
+
+    pub async fn create_answer(&self, offer_sdp: &str) -> Result<String, JsValue> {
+        let mut remote_desc = RtcSessionDescriptionInit::new(RtcSdpType::Offer);
+        remote_desc.sdp(offer_sdp);
+        JsFuture::from(self.connection.set_remote_description(&remote_desc)).await?;
+
+        let answer: RtcSessionDescriptionInit =
+            JsFuture::from(self.connection.create_answer()).await?.unchecked_into();
+        JsFuture::from(self.connection.set_local_description(&answer)).await?;
+        sdp_of(&answer)
+    }
+
+    pub async fn accept_answer(&self, answer_sdp: &str) -> Result<(), JsValue> {
+        let mut remote_desc = RtcSessionDescriptionInit::new(RtcSdpType::Answer);
+        remote_desc.sdp(answer_sdp);
+        JsFuture::from(self.connection.set_remote_description(&remote_desc)).await?;
+        Ok(())
+    }
+
+    pub async fn add_ice_candidate(
+        &self,
+        candidate: &str,
+        sdp_mid: Option<String>,
+        sdp_m_line_index: Option<u16>,
+    ) -> Result<(), JsValue> {
+        let mut init = RtcIceCandidateInit::new(candidate);
+        init.sdp_mid(sdp_mid.as_deref());
+        init.sdp_m_line_index(sdp_m_line_index);
+        JsFuture::from(
+            self.connection
+                .add_ice_candidate_with_opt_rtc_ice_candidate_init(Some(&init)),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub fn hang_up(&self) {
+        if let Some(stream) = self.local_stream.borrow_mut().take() {
+            for track in stream.get_tracks().iter() {
+                track.unchecked_into::<MediaStreamTrack>().stop();
+            }
+        }
+        self.connection.close();
+    }
+}
+
+fn sdp_of(desc: &RtcSessionDescriptionInit) -> Result<String, JsValue> {
+    Ok(js_sys::Reflect::get(desc, &JsValue::from_str("sdp"))?
+        .as_string()
+        .unwrap_or_default())
+}
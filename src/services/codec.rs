@@ -0,0 +1,36 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Wire codec negotiated with the server over the WebSocket connection.
+/// `V1Json` is the original text protocol every server understands;
+/// `V2Bincode` is the compact binary codec newer servers can opt into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CodecVersion {
+    V1Json = 1,
+    V2Bincode = 2,
+}
+
+impl CodecVersion {
+    /// The codec we ask for in the handshake's first frame.
+    pub const PREFERRED: CodecVersion = CodecVersion::V2Bincode;
+
+    pub fn from_handshake_byte(byte: u8) -> Self {
+        match byte {
+            2 => CodecVersion::V2Bincode,
+            _ => CodecVersion::V1Json,
+        }
+    }
+}
+
+pub fn encode<T: Serialize>(version: CodecVersion, value: &T) -> Result<Vec<u8>, String> {
+    match version {
+        CodecVersion::V1Json => serde_json::to_vec(value).map_err(|e| e.to_string()),
+        CodecVersion::V2Bincode => bincode::serialize(value).map_err(|e| e.to_string()),
+    }
+}
+
+pub fn decode<T: DeserializeOwned>(version: CodecVersion, bytes: &[u8]) -> Result<T, String> {
+    match version {
+        CodecVersion::V1Json => serde_json::from_slice(bytes).map_err(|e| e.to_string()),
+        CodecVersion::V2Bincode => bincode::deserialize(bytes).map_err(|e| e.to_string()),
+    }
+}
@@ -0,0 +1,154 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen::closure::Closure;
+use web_sys::{IdbDatabase, IdbOpenDbRequest, IdbTransactionMode};
+
+use crate::components::chat::MessageData;
+
+const DB_NAME: &str = "yewchat_history";
+const DB_VERSION: u32 = 1;
+const STORE_NAME: &str = "messages";
+const MAX_CACHED_MESSAGES: usize = 200;
+
+/// Persists a room's message log to IndexedDB, falling back to `localStorage`
+/// on browsers/contexts where IndexedDB isn't available (e.g. private mode
+/// in some browsers). Reads are synchronous via the localStorage mirror so
+/// `Chat::create` can hydrate `self.room_messages` before the first render;
+/// IndexedDB itself stays write-behind since its API is callback-based.
+pub struct HistoryStore {
+    room: String,
+    /// Cached once the initial `open()` succeeds and reused for every
+    /// subsequent append, instead of opening a fresh connection per message
+    /// (which leaks connections and blocks any future schema upgrade).
+    db: Rc<RefCell<Option<IdbDatabase>>>,
+}
+
+impl HistoryStore {
+    pub fn new(room: &str) -> Self {
+        let db = Rc::new(RefCell::new(None));
+        Self::open_indexed_db(room, db.clone());
+        Self {
+            room: room.to_string(),
+            db,
+        }
+    }
+
+    fn storage_key(room: &str) -> String {
+        format!("yewchat::history::{room}")
+    }
+
+    /// Returns the last known message for the room, if any, so the caller can
+    /// ask the server for a `MsgTypes::History` delta since that point.
+    pub fn last_message(&self) -> Option<MessageData> {
+        self.load().pop()
+    }
+
+    pub fn load(&self) -> Vec<MessageData> {
+        let Some(storage) = local_storage() else {
+            return vec![];
+        };
+        let Ok(Some(raw)) = storage.get_item(&Self::storage_key(&self.room)) else {
+            return vec![];
+        };
+        serde_json::from_str(&raw).unwrap_or_default()
+    }
+
+    pub fn append(&self, message: &MessageData) {
+        let mut messages = self.load();
+        messages.push(message.clone());
+        if messages.len() > MAX_CACHED_MESSAGES {
+            let drop = messages.len() - MAX_CACHED_MESSAGES;
+            messages.drain(0..drop);
+        }
+        self.save(&messages);
+        self.mirror_to_indexed_db(&messages);
+    }
+
+    fn save(&self, messages: &[MessageData]) {
+        let Some(storage) = local_storage() else {
+            return;
+        };
+        if let Ok(serialized) = serde_json::to_string(messages) {
+            let _ = storage.set_item(&Self::storage_key(&self.room), &serialized);
+        }
+    }
+
+    fn mirror_to_indexed_db(&self, messages: &[MessageData]) {
+        let serialized = serde_json::to_string(messages).unwrap_or_default();
+
+        if let Some(db) = self.db.borrow().as_ref() {
+            Self::put(db, &self.room, &serialized);
+            return;
+        }
+
+        // The initial open from `new()` hasn't resolved yet (first append
+        // raced it); open once more, cache the connection, and write once it
+        // comes back so we don't drop this message.
+        let Some(db_req) = open_request() else {
+            return;
+        };
+        let room = self.room.clone();
+        let db_cell = self.db.clone();
+        let onsuccess = Closure::once(move |event: web_sys::Event| {
+            let target = event.target().expect("idb request has a target");
+            let req: IdbOpenDbRequest = target.unchecked_into();
+            if let Ok(result) = req.result() {
+                let db: IdbDatabase = result.unchecked_into();
+                Self::put(&db, &room, &serialized);
+                *db_cell.borrow_mut() = Some(db);
+            }
+        });
+        db_req.set_onsuccess(Some(onsuccess.as_ref().unchecked_ref()));
+        onsuccess.forget();
+    }
+
+    fn put(db: &IdbDatabase, room: &str, serialized: &str) {
+        if let Ok(tx) = db.transaction_with_str_and_mode(STORE_NAME, IdbTransactionMode::Readwrite) {
+            if let Ok(store) = tx.object_store(STORE_NAME) {
+                let _ = store.put_with_key(&JsValue::from_str(serialized), &JsValue::from_str(room));
+            }
+        }
+    }
+
+    fn open_indexed_db(room: &str, db_cell: Rc<RefCell<Option<IdbDatabase>>>) {
+        let Some(db_req) = open_request() else {
+            return;
+        };
+        let onupgradeneeded = Closure::once(move |event: web_sys::Event| {
+            let target = event.target().expect("idb request has a target");
+            let req: IdbOpenDbRequest = target.unchecked_into();
+            if let Ok(result) = req.result() {
+                let db: IdbDatabase = result.unchecked_into();
+                if !db.object_store_names().contains(STORE_NAME) {
+                    let _ = db.create_object_store(STORE_NAME);
+                }
+            }
+        });
+        db_req.set_onupgradeneeded(Some(onupgradeneeded.as_ref().unchecked_ref()));
+        onupgradeneeded.forget();
+
+        let onsuccess = Closure::once(move |event: web_sys::Event| {
+            let target = event.target().expect("idb request has a target");
+            let req: IdbOpenDbRequest = target.unchecked_into();
+            if let Ok(result) = req.result() {
+                let db: IdbDatabase = result.unchecked_into();
+                *db_cell.borrow_mut() = Some(db);
+            }
+        });
+        db_req.set_onsuccess(Some(onsuccess.as_ref().unchecked_ref()));
+        onsuccess.forget();
+        let _ = room;
+    }
+}
+
+fn open_request() -> Option<IdbOpenDbRequest> {
+    let window = web_sys::window()?;
+    let idb = window.indexed_db().ok().flatten()?;
+    idb.open_with_u32(DB_NAME, DB_VERSION).ok()
+}
+
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok().flatten()
+}
@@ -0,0 +1,5 @@
+pub mod call;
+pub mod codec;
+pub mod event_bus;
+pub mod history;
+pub mod websocket;
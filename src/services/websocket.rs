@@ -0,0 +1,142 @@
+use futures::channel::mpsc::{Receiver, Sender};
+use futures::channel::oneshot;
+use futures::{pin_mut, SinkExt, StreamExt};
+use gloo::net::websocket::{futures::WebSocket, Message};
+use gloo::timers::future::TimeoutFuture;
+use wasm_bindgen_futures::spawn_local;
+use yew::Callback;
+use yew_agent::Dispatched;
+
+use crate::components::chat::WebSocketMessage;
+
+use super::codec::{self, CodecVersion};
+use super::event_bus::{EventBus, Request};
+
+const WS_URL: &str = "ws://127.0.0.1:8081/ws";
+const RECONNECT_DELAY_MS: u32 = 2000;
+
+pub struct WebsocketService {
+    pub tx: Sender<String>,
+}
+
+impl WebsocketService {
+    /// `on_connected` fires after every successful handshake, including the
+    /// very first one, so `Chat` can (re-)send session state — registration,
+    /// room joins, history replay requests — that a freshly (re-)opened
+    /// socket doesn't know about yet.
+    pub fn new(on_connected: Callback<()>) -> Self {
+        let (in_tx, in_rx) = futures::channel::mpsc::channel::<String>(1000);
+        spawn_local(Self::run(in_rx, on_connected));
+        Self { tx: in_tx }
+    }
+
+    async fn run(mut in_rx: Receiver<String>, on_connected: Callback<()>) {
+        loop {
+            let ws = match WebSocket::open(WS_URL) {
+                Ok(ws) => ws,
+                Err(e) => {
+                    log::error!("failed to open websocket: {:?}", e);
+                    TimeoutFuture::new(RECONNECT_DELAY_MS).await;
+                    continue;
+                }
+            };
+
+            let (mut write, mut read) = ws.split();
+            let mut event_bus = EventBus::dispatcher();
+
+            // The read side negotiates the codec from the server's handshake
+            // reply and hands the result to the write side over this
+            // oneshot, so outgoing traffic never races the negotiation by
+            // guessing a version before it's known.
+            if let Err(e) = write
+                .send(Message::Bytes(vec![CodecVersion::PREFERRED as u8]))
+                .await
+            {
+                log::error!("failed to send codec handshake: {:?}", e);
+                TimeoutFuture::new(RECONNECT_DELAY_MS).await;
+                continue;
+            }
+            let (version_tx, version_rx) = oneshot::channel::<CodecVersion>();
+
+            let write_task = async {
+                let Ok(version) = version_rx.await else {
+                    return;
+                };
+
+                while let Some(s) = in_rx.next().await {
+                    let Ok(message) = serde_json::from_str::<WebSocketMessage>(&s) else {
+                        log::error!("dropping malformed outgoing message: {s}");
+                        continue;
+                    };
+                    let sent = match version {
+                        CodecVersion::V1Json => match serde_json::to_string(&message) {
+                            Ok(json) => write.send(Message::Text(json)).await,
+                            Err(e) => {
+                                log::error!("failed to encode outgoing message: {e}");
+                                continue;
+                            }
+                        },
+                        CodecVersion::V2Bincode => match codec::encode(version, &message) {
+                            Ok(bytes) => write.send(Message::Bytes(bytes)).await,
+                            Err(e) => {
+                                log::error!("failed to encode outgoing message: {e}");
+                                continue;
+                            }
+                        },
+                    };
+                    if let Err(e) = sent {
+                        log::error!("ws send failed: {:?}", e);
+                        break;
+                    }
+                }
+            };
+
+            let read_task = async {
+                let mut version_tx = Some(version_tx);
+                let mut version = CodecVersion::V1Json;
+
+                while let Some(msg) = read.next().await {
+                    match msg {
+                        Ok(Message::Bytes(bytes)) if version_tx.is_some() && bytes.len() == 1 => {
+                            version = CodecVersion::from_handshake_byte(bytes[0]);
+                            if let Some(tx) = version_tx.take() {
+                                let _ = tx.send(version);
+                            }
+                            on_connected.emit(());
+                        }
+                        Ok(Message::Bytes(bytes)) => {
+                            match codec::decode::<WebSocketMessage>(version, &bytes) {
+                                Ok(message) => match serde_json::to_string(&message) {
+                                    Ok(json) => event_bus.send(Request::EventBusMsg(json)),
+                                    Err(e) => log::error!("failed to re-encode frame as json: {e}"),
+                                },
+                                Err(e) => log::error!("dropping malformed frame: {e}"),
+                            }
+                        }
+                        Ok(Message::Text(data)) => {
+                            // Server doesn't speak the binary codec at all;
+                            // the frame we just received is already a real
+                            // message.
+                            if let Some(tx) = version_tx.take() {
+                                version = CodecVersion::V1Json;
+                                let _ = tx.send(version);
+                                on_connected.emit(());
+                            }
+                            event_bus.send(Request::EventBusMsg(data));
+                        }
+                        Err(e) => {
+                            log::error!("ws: {:?}", e);
+                            break;
+                        }
+                    }
+                }
+            };
+
+            pin_mut!(write_task, read_task);
+            futures::future::select(write_task, read_task).await;
+
+            log::debug!("websocket disconnected, reconnecting");
+            TimeoutFuture::new(RECONNECT_DELAY_MS).await;
+        }
+    }
+}